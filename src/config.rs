@@ -5,14 +5,21 @@ use clap::{Parser, Arg};
 #[derive(Parser, Debug)]
 #[command(author, version, about, long_about = None)]
 pub struct Args {
-    /// Address of the node to connect to.
-    #[arg(short, long, default_value = "79.56.220.96:8333")]
+    /// Address of the node to connect to. May omit the port, in which case
+    /// the default port for `--network` is used.
+    #[arg(short, long, default_value = "79.56.220.96")]
     pub remote_address: String,
 
-    /// Local address of this node.
-    #[arg(short, long, default_value = "0.0.0.0:8333")]
+    /// Local address of this node. May omit the port, in which case the
+    /// default port for `--network` is used.
+    #[arg(short, long, default_value = "0.0.0.0")]
     pub local_address: String,
 
+    /// Which Bitcoin network to crawl: `bitcoin`, `testnet`, `signet`, or
+    /// `regtest`. Governs the message magic and the default port.
+    #[arg(long, default_value = "bitcoin")]
+    pub network: String,
+
     /// Maximum number of peer addresses to collect.
     #[arg(long, default_value_t = 50)]
     pub address_limit: usize,
@@ -24,4 +31,55 @@ pub struct Args {
     /// User agent string to use in the version message.
     #[arg(long, default_value = "/Satoshi:25.0.0/")]
     pub user_agent: String,
+
+    /// Path to the on-disk address datastore. Loaded on startup and
+    /// persisted periodically and on shutdown so crawls resume.
+    #[arg(long, default_value = "peers.csv")]
+    pub datastore_path: String,
+
+    /// How often, in seconds, to flush the datastore to disk while running.
+    #[arg(long, default_value_t = 60)]
+    pub save_interval: u64,
+
+    /// If set, run a DNS-seed responder bound to this address instead of
+    /// exiting once the crawl finishes, e.g. `0.0.0.0:53`.
+    #[arg(long)]
+    pub serve_dns: Option<String>,
+
+    /// DNS zone the seed responder answers for, e.g. `seed.example.com`.
+    #[arg(long, default_value = "seed.example.com")]
+    pub dns_zone: String,
+
+    /// Which address families to crawl and collect, as a comma-separated
+    /// list of `ipv4`, `ipv6`, `onion`.
+    #[arg(long, default_value = "ipv4,ipv6", value_delimiter = ',')]
+    pub address_families: Vec<String>,
+
+    /// SOCKS5 proxy (e.g. a local Tor daemon) used to dial `.onion` peers.
+    /// Required if `onion` is included in `--address-families`.
+    #[arg(long)]
+    pub proxy: Option<String>,
+
+    /// Maximum number of peer connections to have in flight at once.
+    #[arg(long, default_value_t = 64)]
+    pub max_concurrency: usize,
+
+    /// Minimum estimated chain tip height (see `--locator-block-height`)
+    /// for a peer to be classified `HighBlockCount` instead of
+    /// `LowBlockCount`.
+    #[arg(long, default_value_t = 800_000)]
+    pub min_block_height: i32,
+
+    /// Hash of a recent block used to build the `getheaders` locator sent
+    /// while probing a peer's chain. Should be updated occasionally to
+    /// stay recent, together with `--locator-block-height`.
+    #[arg(long, default_value = "00000000000000000002a7c4c1e48d76c5a37902165a270156b7a8d72728a6e")]
+    pub locator_block_hash: String,
+
+    /// Height of the block identified by `--locator-block-hash`. A peer's
+    /// chain tip is estimated as this height plus however many headers it
+    /// returns for our `getheaders`, rather than trusting its self-reported
+    /// `version.start_height`.
+    #[arg(long, default_value_t = 800_000)]
+    pub locator_block_height: i32,
 }
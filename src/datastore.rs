@@ -0,0 +1,456 @@
+// datastore.rs
+//
+// A small, on-disk peer address store modeled on the address classification
+// scheme used by Bitcoin Core's `dnsseed` tool. Instead of a flat set of
+// addresses that are crawled once and discarded, every address we learn
+// about is tracked as a `PeerEntry` that carries its own retry schedule, so
+// a long-running crawl behaves like a node monitor rather than a one-shot
+// scrape.
+
+use std::collections::HashMap;
+use std::fs::{self, File};
+use std::io::{BufRead, BufReader, Write};
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use bitcoin::p2p::ServiceFlags;
+
+use crate::messaging::PeerCapabilities;
+use crate::netaddr::PeerAddress;
+
+/// Classification of a known address, mirroring the states a dnsseed
+/// crawler assigns as it learns more about a peer.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum AddressState {
+    /// Never successfully connected to.
+    Untested,
+    /// Connected, but the peer's chain tip is below our threshold.
+    LowBlockCount,
+    /// Connected and the peer's chain tip looks current.
+    HighBlockCount,
+    /// A full handshake plus a recent successful re-check.
+    Good,
+    /// Used to be `Good` or `HighBlockCount` but has since failed a re-check.
+    WasGood,
+    /// The peer's advertised protocol/services rule it out; it is only
+    /// re-checked once a day.
+    ProtocolDisabled,
+    /// The crawl attempt failed with an error, e.g. the TCP connection
+    /// timed out or was refused.
+    Timeout,
+    /// The connection succeeded but a subsequent request timed out.
+    TimeoutDuringRequest,
+    /// Connected but the peer never sent `version`.
+    TimeoutAwaitingVersion,
+    /// Connected but the peer never sent `verack`.
+    TimeoutAwaitingVerack,
+}
+
+impl AddressState {
+    /// How long an entry in this state should be left alone before it is
+    /// eligible to be tested again.
+    fn retry_interval_secs(self) -> i64 {
+        const MINUTE: i64 = 60;
+        match self {
+            AddressState::Untested => 0,
+            AddressState::Good => 30 * MINUTE,
+            AddressState::HighBlockCount => 30 * MINUTE,
+            AddressState::LowBlockCount => 60 * MINUTE,
+            AddressState::WasGood => 15 * MINUTE,
+            AddressState::Timeout => 6 * 60 * MINUTE,
+            AddressState::TimeoutDuringRequest
+            | AddressState::TimeoutAwaitingVersion
+            | AddressState::TimeoutAwaitingVerack => 3 * 60 * MINUTE,
+            AddressState::ProtocolDisabled => 24 * 60 * MINUTE,
+        }
+    }
+
+    fn as_str(self) -> &'static str {
+        match self {
+            AddressState::Untested => "untested",
+            AddressState::LowBlockCount => "low_block_count",
+            AddressState::HighBlockCount => "high_block_count",
+            AddressState::Good => "good",
+            AddressState::WasGood => "was_good",
+            AddressState::ProtocolDisabled => "protocol_disabled",
+            AddressState::Timeout => "timeout",
+            AddressState::TimeoutDuringRequest => "timeout_during_request",
+            AddressState::TimeoutAwaitingVersion => "timeout_awaiting_version",
+            AddressState::TimeoutAwaitingVerack => "timeout_awaiting_verack",
+        }
+    }
+}
+
+impl FromStr for AddressState {
+    type Err = ();
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Ok(match s {
+            "untested" => AddressState::Untested,
+            "low_block_count" => AddressState::LowBlockCount,
+            "high_block_count" => AddressState::HighBlockCount,
+            "good" => AddressState::Good,
+            "was_good" => AddressState::WasGood,
+            "protocol_disabled" => AddressState::ProtocolDisabled,
+            "timeout" => AddressState::Timeout,
+            "timeout_during_request" => AddressState::TimeoutDuringRequest,
+            "timeout_awaiting_version" => AddressState::TimeoutAwaitingVersion,
+            "timeout_awaiting_verack" => AddressState::TimeoutAwaitingVerack,
+            _ => return Err(()),
+        })
+    }
+}
+
+/// Everything we know about a single address.
+#[derive(Debug, Clone)]
+pub struct PeerEntry {
+    pub state: AddressState,
+    /// Unix timestamp of the last successful connection to this peer.
+    /// Learning the address from an `addr` relay does not set it.
+    pub last_seen: Option<i64>,
+    /// Unix timestamp of the last time we attempted to connect.
+    pub last_tried: Option<i64>,
+    pub services: ServiceFlags,
+    pub protocol_version: Option<i32>,
+    pub start_height: Option<i32>,
+    /// Feature messages negotiated during the last successful handshake.
+    pub capabilities: PeerCapabilities,
+}
+
+impl Default for PeerEntry {
+    fn default() -> Self {
+        PeerEntry {
+            state: AddressState::Untested,
+            last_seen: None,
+            last_tried: None,
+            services: ServiceFlags::NONE,
+            protocol_version: None,
+            start_height: None,
+            capabilities: PeerCapabilities::default(),
+        }
+    }
+}
+
+/// The outcome of a single crawl attempt against an address, used to update
+/// its entry in the store.
+#[derive(Debug, Clone, Copy)]
+pub enum CrawlOutcome {
+    Good { services: ServiceFlags, protocol_version: i32, start_height: i32, capabilities: PeerCapabilities },
+    HighBlockCount { services: ServiceFlags, protocol_version: i32, start_height: i32, capabilities: PeerCapabilities },
+    LowBlockCount { services: ServiceFlags, protocol_version: i32, start_height: i32, capabilities: PeerCapabilities },
+    Timeout,
+    TimeoutDuringRequest,
+    TimeoutAwaitingVersion,
+    TimeoutAwaitingVerack,
+    ProtocolDisabled,
+}
+
+/// A persistent table of every address the crawler has ever learned about,
+/// along with its current classification and retry schedule.
+pub struct DataStore {
+    path: PathBuf,
+    entries: HashMap<PeerAddress, PeerEntry>,
+}
+
+impl DataStore {
+    /// Load the store from `path`, or start empty if the file does not
+    /// exist yet.
+    pub fn load_or_create(path: impl Into<PathBuf>) -> Self {
+        let path = path.into();
+        let entries = Self::read_entries(&path).unwrap_or_default();
+        DataStore { path, entries }
+    }
+
+    fn read_entries(path: &Path) -> Option<HashMap<PeerAddress, PeerEntry>> {
+        let file = File::open(path).ok()?;
+        let mut entries = HashMap::new();
+        for line in BufReader::new(file).lines() {
+            let line = line.ok()?;
+            if line.trim().is_empty() {
+                continue;
+            }
+            if let Some(entry) = Self::parse_line(&line) {
+                entries.insert(entry.0, entry.1);
+            }
+        }
+        Some(entries)
+    }
+
+    fn parse_line(line: &str) -> Option<(PeerAddress, PeerEntry)> {
+        // addr,state,last_seen,last_tried,services,protocol_version,start_height,capabilities
+        let mut fields = line.split(',');
+        let addr = fields.next()?.parse().ok()?;
+        let state = fields.next()?.parse().ok()?;
+        let last_seen = parse_opt_i64(fields.next()?);
+        let last_tried = parse_opt_i64(fields.next()?);
+        let services = ServiceFlags::from(fields.next()?.parse::<u64>().ok()?);
+        let protocol_version = parse_opt_i32(fields.next()?);
+        let start_height = parse_opt_i32(fields.next()?);
+        // Older datastores predate capability tracking; default to none.
+        let capabilities = fields
+            .next()
+            .and_then(|field| field.parse::<u8>().ok())
+            .map(PeerCapabilities::from_bits)
+            .unwrap_or_default();
+        Some((
+            addr,
+            PeerEntry {
+                state,
+                last_seen,
+                last_tried,
+                services,
+                protocol_version,
+                start_height,
+                capabilities,
+            },
+        ))
+    }
+
+    /// Write the full table to disk, overwriting any previous snapshot.
+    pub fn save(&self) -> anyhow::Result<()> {
+        if let Some(parent) = self.path.parent() {
+            if !parent.as_os_str().is_empty() {
+                fs::create_dir_all(parent)?;
+            }
+        }
+        let mut file = File::create(&self.path)?;
+        for (addr, entry) in &self.entries {
+            writeln!(
+                file,
+                "{},{},{},{},{},{},{},{}",
+                addr,
+                entry.state.as_str(),
+                fmt_opt(entry.last_seen),
+                fmt_opt(entry.last_tried),
+                entry.services.to_u64(),
+                fmt_opt(entry.protocol_version),
+                fmt_opt(entry.start_height),
+                entry.capabilities.to_bits(),
+            )?;
+        }
+        Ok(())
+    }
+
+    /// Record a freshly discovered address. No-op if we already know about
+    /// it so an existing classification isn't clobbered.
+    pub fn observe(&mut self, address: PeerAddress) {
+        self.entries.entry(address).or_insert_with(PeerEntry::default);
+    }
+
+    /// Update an entry after a crawl attempt completes.
+    pub fn record_outcome(&mut self, address: PeerAddress, outcome: CrawlOutcome) {
+        let now = now_unix();
+        let entry = self.entries.entry(address).or_insert_with(PeerEntry::default);
+        entry.last_tried = Some(now);
+        match outcome {
+            CrawlOutcome::Good { services, protocol_version, start_height, capabilities } => {
+                entry.state = AddressState::Good;
+                entry.services = services;
+                entry.protocol_version = Some(protocol_version);
+                entry.start_height = Some(start_height);
+                entry.capabilities = capabilities;
+                entry.last_seen = Some(now);
+            }
+            CrawlOutcome::HighBlockCount { services, protocol_version, start_height, capabilities } => {
+                entry.state = Self::promote(entry.state);
+                entry.services = services;
+                entry.protocol_version = Some(protocol_version);
+                entry.start_height = Some(start_height);
+                entry.capabilities = capabilities;
+                entry.last_seen = Some(now);
+            }
+            CrawlOutcome::LowBlockCount { services, protocol_version, start_height, capabilities } => {
+                entry.state = AddressState::LowBlockCount;
+                entry.services = services;
+                entry.protocol_version = Some(protocol_version);
+                entry.start_height = Some(start_height);
+                entry.capabilities = capabilities;
+                entry.last_seen = Some(now);
+            }
+            CrawlOutcome::Timeout => {
+                entry.state = Self::demote(entry.state, AddressState::Timeout);
+            }
+            CrawlOutcome::TimeoutDuringRequest => {
+                entry.state = Self::demote(entry.state, AddressState::TimeoutDuringRequest);
+            }
+            CrawlOutcome::TimeoutAwaitingVersion => {
+                entry.state = Self::demote(entry.state, AddressState::TimeoutAwaitingVersion);
+            }
+            CrawlOutcome::TimeoutAwaitingVerack => {
+                entry.state = Self::demote(entry.state, AddressState::TimeoutAwaitingVerack);
+            }
+            CrawlOutcome::ProtocolDisabled => {
+                entry.state = AddressState::ProtocolDisabled;
+            }
+        }
+    }
+
+    /// A peer that was `Good`, `HighBlockCount` or `WasGood` and fails a
+    /// re-check becomes (or stays) `WasGood` rather than dropping straight
+    /// to the failure state, so it gets re-tried sooner than a peer that
+    /// was never any good.
+    fn demote(previous: AddressState, failure: AddressState) -> AddressState {
+        if matches!(previous, AddressState::Good | AddressState::HighBlockCount | AddressState::WasGood) {
+            AddressState::WasGood
+        } else {
+            failure
+        }
+    }
+
+    /// A peer that clears the block-height probe for the first time lands
+    /// at `HighBlockCount` rather than `Good` - it's only promoted to `Good`
+    /// once it clears the probe again on a later re-check, the same
+    /// "prove it twice" pattern `demote` uses on the way down.
+    fn promote(previous: AddressState) -> AddressState {
+        if matches!(previous, AddressState::HighBlockCount | AddressState::Good) {
+            AddressState::Good
+        } else {
+            AddressState::HighBlockCount
+        }
+    }
+
+    /// Return up to `limit` addresses whose retry interval has elapsed,
+    /// oldest `last_tried` first.
+    pub fn next_batch(&self, limit: usize) -> Vec<PeerAddress> {
+        let now = now_unix();
+        let mut due: Vec<(PeerAddress, i64)> = self
+            .entries
+            .iter()
+            .filter_map(|(addr, entry)| {
+                let due_at = entry.last_tried.unwrap_or(0) + entry.state.retry_interval_secs();
+                if due_at <= now {
+                    Some((addr.clone(), entry.last_tried.unwrap_or(0)))
+                } else {
+                    None
+                }
+            })
+            .collect();
+        due.sort_by_key(|(_, last_tried)| *last_tried);
+        due.into_iter().take(limit).map(|(addr, _)| addr).collect()
+    }
+
+    /// All addresses currently classified as `Good`.
+    pub fn good_addresses(&self) -> impl Iterator<Item = (&PeerAddress, &PeerEntry)> {
+        self.entries.iter().filter(|(_, entry)| entry.state == AddressState::Good)
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+}
+
+fn now_unix() -> i64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs() as i64)
+        .unwrap_or(0)
+}
+
+fn fmt_opt<T: std::fmt::Display>(value: Option<T>) -> String {
+    match value {
+        Some(v) => v.to_string(),
+        None => String::from("-"),
+    }
+}
+
+fn parse_opt_i64(field: &str) -> Option<i64> {
+    if field == "-" {
+        None
+    } else {
+        field.parse().ok()
+    }
+}
+
+fn parse_opt_i32(field: &str) -> Option<i32> {
+    if field == "-" {
+        None
+    } else {
+        field.parse().ok()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::net::{IpAddr, Ipv4Addr, SocketAddr};
+
+    fn addr(port: u16) -> PeerAddress {
+        PeerAddress::Socket(SocketAddr::new(IpAddr::V4(Ipv4Addr::new(127, 0, 0, 1)), port))
+    }
+
+    #[test]
+    fn untested_addresses_are_immediately_due() {
+        let mut store = DataStore::load_or_create("/tmp/does-not-exist-datastore-test.csv");
+        store.observe(addr(8333));
+        assert_eq!(store.next_batch(10), vec![addr(8333)]);
+    }
+
+    #[test]
+    fn good_peers_are_not_due_immediately_after_success() {
+        let mut store = DataStore::load_or_create("/tmp/does-not-exist-datastore-test-2.csv");
+        store.observe(addr(8333));
+        store.record_outcome(
+            addr(8333),
+            CrawlOutcome::Good { services: ServiceFlags::NETWORK, protocol_version: 70016, start_height: 800_000, capabilities: PeerCapabilities::default() },
+        );
+        assert!(store.next_batch(10).is_empty());
+    }
+
+    #[test]
+    fn a_good_peer_that_fails_becomes_was_good() {
+        let mut store = DataStore::load_or_create("/tmp/does-not-exist-datastore-test-3.csv");
+        store.observe(addr(8333));
+        store.record_outcome(
+            addr(8333),
+            CrawlOutcome::Good { services: ServiceFlags::NETWORK, protocol_version: 70016, start_height: 800_000, capabilities: PeerCapabilities::default() },
+        );
+        store.record_outcome(addr(8333), CrawlOutcome::Timeout);
+        assert_eq!(store.entries.get(&addr(8333)).unwrap().state, AddressState::WasGood);
+    }
+
+    #[test]
+    fn a_first_high_block_count_pass_does_not_immediately_become_good() {
+        let mut store = DataStore::load_or_create("/tmp/does-not-exist-datastore-test-4.csv");
+        store.observe(addr(8333));
+        store.record_outcome(
+            addr(8333),
+            CrawlOutcome::HighBlockCount { services: ServiceFlags::NETWORK, protocol_version: 70016, start_height: 800_000, capabilities: PeerCapabilities::default() },
+        );
+        assert_eq!(store.entries.get(&addr(8333)).unwrap().state, AddressState::HighBlockCount);
+    }
+
+    #[test]
+    fn a_second_high_block_count_pass_promotes_to_good() {
+        let mut store = DataStore::load_or_create("/tmp/does-not-exist-datastore-test-5.csv");
+        store.observe(addr(8333));
+        let outcome = CrawlOutcome::HighBlockCount { services: ServiceFlags::NETWORK, protocol_version: 70016, start_height: 800_000, capabilities: PeerCapabilities::default() };
+        store.record_outcome(addr(8333), outcome);
+        store.record_outcome(addr(8333), outcome);
+        assert_eq!(store.entries.get(&addr(8333)).unwrap().state, AddressState::Good);
+    }
+
+    #[test]
+    fn round_trips_through_disk() {
+        let path = "/tmp/datastore-round-trip-test.csv";
+        let _ = fs::remove_file(path);
+        let mut store = DataStore::load_or_create(path);
+        store.observe(addr(8333));
+        store.record_outcome(
+            addr(8333),
+            CrawlOutcome::Good { services: ServiceFlags::NETWORK, protocol_version: 70016, start_height: 800_000, capabilities: PeerCapabilities::default() },
+        );
+        store.save().unwrap();
+
+        let reloaded = DataStore::load_or_create(path);
+        assert_eq!(reloaded.len(), 1);
+        let entry = reloaded.entries.get(&addr(8333)).unwrap();
+        assert_eq!(entry.state, AddressState::Good);
+        assert_eq!(entry.services, ServiceFlags::NETWORK);
+        let _ = fs::remove_file(path);
+    }
+}
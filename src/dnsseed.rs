@@ -0,0 +1,317 @@
+// dnsseed.rs
+//
+// A minimal UDP DNS responder that turns the crawler into a Bitcoin-style
+// DNS seed: once the datastore has classified peers, this answers A/AAAA
+// queries for the configured zone with a random subset of `Good` addresses,
+// the same bootstrap mechanism the crawler itself climbs toward.
+//
+// This implements just enough of RFC 1035 to answer simple A/AAAA
+// questions - no compression pointers in the question section, no
+// recursion, no EDNS - which is all a seed responder needs.
+
+use std::net::{Ipv4Addr, Ipv6Addr, SocketAddr};
+use std::sync::Arc;
+
+use bitcoin::p2p::ServiceFlags;
+use rand::seq::SliceRandom;
+use tokio::net::UdpSocket;
+use tokio::sync::Mutex;
+
+use crate::datastore::DataStore;
+
+/// Upper bound on answer records regardless of packet size, kept as a sanity
+/// cap independent of `max_answers_for`.
+const MAX_ANSWERS: usize = 24;
+
+/// Classic UDP DNS responses are limited to 512 bytes without EDNS, which
+/// this responder doesn't negotiate.
+const MAX_PACKET_LEN: usize = 512;
+
+/// Fixed per-answer overhead before the address itself: a compressed name
+/// pointer (2), type (2), class (2), TTL (4), and RDLENGTH (2).
+const ANSWER_FIXED_LEN: usize = 12;
+
+/// Only hand out addresses we have heard from within this many seconds.
+const MAX_ADDRESS_AGE_SECS: i64 = 3 * 60 * 60;
+
+const QTYPE_A: u16 = 1;
+const QTYPE_AAAA: u16 = 28;
+const QCLASS_IN: u16 = 1;
+const ANSWER_TTL: u32 = 60;
+
+/// Run the DNS-seed responder until the process is terminated.
+pub async fn serve(bind_addr: SocketAddr, zone: String, store: Arc<Mutex<DataStore>>) -> anyhow::Result<()> {
+    let socket = UdpSocket::bind(bind_addr).await?;
+    tracing::info!("DNS seed responder listening on {} for zone {}", bind_addr, zone);
+
+    let mut buf = [0u8; 512];
+    loop {
+        let (len, from) = match socket.recv_from(&mut buf).await {
+            Ok(v) => v,
+            Err(e) => {
+                tracing::error!("dnsseed: recv_from failed: {:?}", e);
+                continue;
+            }
+        };
+
+        match handle_query(&buf[..len], &zone, &store).await {
+            Ok(Some(response)) => {
+                if let Err(e) = socket.send_to(&response, from).await {
+                    tracing::error!("dnsseed: send_to {} failed: {:?}", from, e);
+                }
+            }
+            Ok(None) => {
+                // Query was unparseable or wasn't for our zone; drop it.
+            }
+            Err(e) => {
+                tracing::warn!("dnsseed: failed to handle query from {}: {:?}", from, e);
+            }
+        }
+    }
+}
+
+async fn handle_query(packet: &[u8], zone: &str, store: &Arc<Mutex<DataStore>>) -> anyhow::Result<Option<Vec<u8>>> {
+    let query = match DnsQuery::parse(packet)? {
+        Some(q) => q,
+        None => return Ok(None),
+    };
+
+    if !query.name_in_zone(zone) {
+        return Ok(None);
+    }
+
+    if query.qtype != QTYPE_A && query.qtype != QTYPE_AAAA {
+        return Ok(Some(encode_empty_response(&query)));
+    }
+
+    let service_filter = query.service_filter();
+
+    let addresses = {
+        let store = store.lock().await;
+        collect_candidates(&store, query.qtype, service_filter, query.raw_question.len())
+    };
+
+    Ok(Some(encode_response(&query, &addresses)))
+}
+
+/// How many answer records of this `qtype` fit in a 512-byte response
+/// alongside the given (already-encoded) question, so a full batch of AAAA
+/// answers can't overflow the classic UDP DNS packet limit the way a flat
+/// `MAX_ANSWERS` would.
+fn max_answers_for(qtype: u16, question_len: usize) -> usize {
+    let rdata_len = match qtype {
+        QTYPE_AAAA => 16,
+        _ => 4,
+    };
+    let fixed_overhead = 12 + question_len; // header + echoed question
+    let per_answer = ANSWER_FIXED_LEN + rdata_len;
+    let budget = MAX_PACKET_LEN.saturating_sub(fixed_overhead);
+    (budget / per_answer).clamp(1, MAX_ANSWERS)
+}
+
+/// Pull addresses of the right family that are `Good`, recent, and (if a
+/// service filter was encoded in the query name) advertise those services.
+fn collect_candidates(store: &DataStore, qtype: u16, service_filter: Option<ServiceFlags>, question_len: usize) -> Vec<SocketAddr> {
+    let now = now_unix();
+    let mut candidates: Vec<SocketAddr> = store
+        .good_addresses()
+        .filter(|(_, entry)| {
+            entry.last_seen.map(|seen| now - seen <= MAX_ADDRESS_AGE_SECS).unwrap_or(false)
+        })
+        .filter(|(_, entry)| match service_filter {
+            Some(required) => entry.services.has(required),
+            None => true,
+        })
+        // A DNS seed can only answer with routable IPs; onion peers have
+        // no A/AAAA record to hand out.
+        .filter_map(|(addr, _)| addr.as_socket_addr())
+        .filter(|addr| match qtype {
+            QTYPE_A => addr.is_ipv4(),
+            QTYPE_AAAA => addr.is_ipv6(),
+            _ => false,
+        })
+        .collect();
+
+    candidates.shuffle(&mut rand::thread_rng());
+    candidates.truncate(max_answers_for(qtype, question_len));
+    candidates
+}
+
+fn now_unix() -> i64 {
+    use std::time::{SystemTime, UNIX_EPOCH};
+    SystemTime::now().duration_since(UNIX_EPOCH).map(|d| d.as_secs() as i64).unwrap_or(0)
+}
+
+/// A parsed DNS question, along with the header fields needed to build a
+/// matching response.
+struct DnsQuery {
+    id: u16,
+    labels: Vec<String>,
+    qtype: u16,
+    qclass: u16,
+    /// The raw question bytes copied from the original packet, reused
+    /// verbatim in the response (avoids re-encoding the name).
+    raw_question: Vec<u8>,
+}
+
+impl DnsQuery {
+    /// Parse a single-question query. Returns `Ok(None)` for anything we
+    /// don't understand (multiple questions, compressed names in the
+    /// question, truncated packets) rather than erroring, since a seed
+    /// responder should just silently ignore noise.
+    fn parse(packet: &[u8]) -> anyhow::Result<Option<DnsQuery>> {
+        if packet.len() < 12 {
+            return Ok(None);
+        }
+        let id = u16::from_be_bytes([packet[0], packet[1]]);
+        let qdcount = u16::from_be_bytes([packet[4], packet[5]]);
+        if qdcount != 1 {
+            return Ok(None);
+        }
+
+        let mut pos = 12;
+        let mut labels = Vec::new();
+        loop {
+            if pos >= packet.len() {
+                return Ok(None);
+            }
+            let len = packet[pos] as usize;
+            if len == 0 {
+                pos += 1;
+                break;
+            }
+            if len & 0xC0 != 0 {
+                // Name compression in a question is not something a real
+                // client sends; treat it as unparseable.
+                return Ok(None);
+            }
+            pos += 1;
+            if pos + len > packet.len() {
+                return Ok(None);
+            }
+            let label = String::from_utf8_lossy(&packet[pos..pos + len]).to_lowercase();
+            labels.push(label);
+            pos += len;
+        }
+
+        if pos + 4 > packet.len() {
+            return Ok(None);
+        }
+        let qtype = u16::from_be_bytes([packet[pos], packet[pos + 1]]);
+        let qclass = u16::from_be_bytes([packet[pos + 2], packet[pos + 3]]);
+        if qclass != QCLASS_IN {
+            return Ok(None);
+        }
+        let raw_question = packet[12..pos + 4].to_vec();
+
+        Ok(Some(DnsQuery { id, labels, qtype, qclass, raw_question }))
+    }
+
+    /// Whether the queried name falls under `zone`, e.g. `seed.example.com`
+    /// or `x9.seed.example.com` both match zone `seed.example.com`.
+    fn name_in_zone(&self, zone: &str) -> bool {
+        let zone_labels: Vec<&str> = zone.trim_end_matches('.').split('.').collect();
+        if self.labels.len() < zone_labels.len() {
+            return false;
+        }
+        let suffix = &self.labels[self.labels.len() - zone_labels.len()..];
+        suffix.iter().map(String::as_str).eq(zone_labels.iter().copied().map(str::to_lowercase).collect::<Vec<_>>().iter().map(String::as_str))
+    }
+
+    /// Decode a service-flag filter from the leftmost label, if present,
+    /// e.g. `x9.seed.example.com` requests peers advertising the service
+    /// bits in hex value `9` (NODE_NETWORK | NODE_WITNESS would be encoded
+    /// the same way real dnsseed subdomains work).
+    fn service_filter(&self) -> Option<ServiceFlags> {
+        let leftmost = self.labels.first()?;
+        let hex = leftmost.strip_prefix('x')?;
+        let bits = u64::from_str_radix(hex, 16).ok()?;
+        Some(ServiceFlags::from(bits))
+    }
+}
+
+fn encode_empty_response(query: &DnsQuery) -> Vec<u8> {
+    encode_response(query, &[])
+}
+
+fn encode_response(query: &DnsQuery, addresses: &[SocketAddr]) -> Vec<u8> {
+    let mut out = Vec::with_capacity(64 + addresses.len() * 20);
+
+    // Header: id, flags (response, no error, authoritative), counts.
+    out.extend_from_slice(&query.id.to_be_bytes());
+    out.extend_from_slice(&0x8580u16.to_be_bytes()); // QR=1, AA=1, RD=1, RA=1, RCODE=0
+    out.extend_from_slice(&1u16.to_be_bytes()); // QDCOUNT
+    out.extend_from_slice(&(addresses.len() as u16).to_be_bytes()); // ANCOUNT
+    out.extend_from_slice(&0u16.to_be_bytes()); // NSCOUNT
+    out.extend_from_slice(&0u16.to_be_bytes()); // ARCOUNT
+
+    // Echo the question section back verbatim.
+    out.extend_from_slice(&query.raw_question);
+
+    for addr in addresses {
+        // Name pointer back to the question's name at offset 12.
+        out.extend_from_slice(&0xC00Cu16.to_be_bytes());
+        out.extend_from_slice(&query.qtype.to_be_bytes());
+        out.extend_from_slice(&query.qclass.to_be_bytes());
+        out.extend_from_slice(&ANSWER_TTL.to_be_bytes());
+        match addr {
+            SocketAddr::V4(v4) => {
+                out.extend_from_slice(&4u16.to_be_bytes());
+                out.extend_from_slice(&v4.ip().octets());
+            }
+            SocketAddr::V6(v6) => {
+                out.extend_from_slice(&16u16.to_be_bytes());
+                out.extend_from_slice(&v6.ip().octets());
+            }
+        }
+    }
+
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn build_query(name: &str, qtype: u16) -> Vec<u8> {
+        let mut packet = Vec::new();
+        packet.extend_from_slice(&1234u16.to_be_bytes()); // id
+        packet.extend_from_slice(&0x0100u16.to_be_bytes()); // flags: recursion desired
+        packet.extend_from_slice(&1u16.to_be_bytes()); // qdcount
+        packet.extend_from_slice(&0u16.to_be_bytes());
+        packet.extend_from_slice(&0u16.to_be_bytes());
+        packet.extend_from_slice(&0u16.to_be_bytes());
+        for label in name.split('.') {
+            packet.push(label.len() as u8);
+            packet.extend_from_slice(label.as_bytes());
+        }
+        packet.push(0);
+        packet.extend_from_slice(&qtype.to_be_bytes());
+        packet.extend_from_slice(&QCLASS_IN.to_be_bytes());
+        packet
+    }
+
+    #[test]
+    fn parses_a_simple_query() {
+        let packet = build_query("seed.example.com", QTYPE_A);
+        let query = DnsQuery::parse(&packet).unwrap().unwrap();
+        assert_eq!(query.qtype, QTYPE_A);
+        assert_eq!(query.labels, vec!["seed", "example", "com"]);
+        assert!(query.name_in_zone("seed.example.com"));
+    }
+
+    #[test]
+    fn decodes_service_filter_from_leftmost_label() {
+        let packet = build_query("x9.seed.example.com", QTYPE_A);
+        let query = DnsQuery::parse(&packet).unwrap().unwrap();
+        assert!(query.name_in_zone("seed.example.com"));
+        assert_eq!(query.service_filter(), Some(ServiceFlags::from(0x9)));
+    }
+
+    #[test]
+    fn rejects_names_outside_the_zone() {
+        let packet = build_query("seed.other.com", QTYPE_A);
+        let query = DnsQuery::parse(&packet).unwrap().unwrap();
+        assert!(!query.name_in_zone("seed.example.com"));
+    }
+}
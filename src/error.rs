@@ -15,4 +15,6 @@ pub enum Error {
     SendingFailed(io::Error),
     #[error("Invalid address format for {0}")]
     InvalidAddress(String),
+    #[error("Invalid network: {0}")]
+    InvalidNetwork(String),
 }
@@ -48,27 +48,73 @@ mod utils;
 mod messaging;
 mod error;
 mod config;
+mod datastore;
+mod dnsseed;
+mod netaddr;
+mod socks;
 
-use std::collections::{HashSet, VecDeque};
-use std::net::SocketAddr;
+use std::collections::HashSet;
+use std::net::{SocketAddr, SocketAddrV4, Ipv4Addr};
+use std::sync::Arc;
 use std::time::Duration;
 
 use anyhow::{Context, Result};
+use bitcoin::hashes::Hash;
+use bitcoin::p2p::address::AddrV2Message;
 use bitcoin::p2p::message::{NetworkMessage, RawNetworkMessage};
-use bitcoin::p2p::message_network::VersionMessage;
-use bitcoin::Network;
+use bitcoin::p2p::message_blockdata::GetHeadersMessage;
+use bitcoin::p2p::ServiceFlags;
+use bitcoin::{BlockHash, Network};
 use config::Args;
 use codec::BitcoinCodec;
-use futures::{SinkExt, StreamExt, TryFutureExt, future::join_all};
+use datastore::{CrawlOutcome, DataStore};
+use futures::{SinkExt, StreamExt, TryFutureExt};
+use netaddr::{AddressFamily, PeerAddress};
 use tokio::net::TcpStream;
-use tokio::time::{timeout};
+use tokio::sync::{mpsc, Mutex, Semaphore};
+use tokio::time::{interval, sleep_until, timeout};
 use tokio_util::codec::Framed;
 use clap::Parser;
 
-use utils::{init_tracing, is_ipv4};
-use messaging::build_version_message;
+use utils::{init_tracing, address_family};
+use messaging::{perform_handshake, HandshakeOutcome, PeerCapabilities};
 use error::Error;
 
+/// The result of a successful handshake-and-harvest against a peer.
+struct CrawlSuccess {
+    addresses: HashSet<PeerAddress>,
+    services: ServiceFlags,
+    protocol_version: i32,
+    start_height: i32,
+    capabilities: PeerCapabilities,
+    chain_tip_estimate: i32,
+}
+
+/// The outcome of a single crawl attempt, distinguishing a clean success
+/// from the various points a peer can go silent - each maps to a distinct
+/// `CrawlOutcome` so the datastore can tell them apart.
+enum CrawlAttempt {
+    Success(CrawlSuccess),
+    TimeoutAwaitingVersion,
+    TimeoutAwaitingVerack,
+    TimeoutDuringRequest,
+}
+
+/// Parse `--address-families` into the set of families we'll crawl and
+/// collect, skipping (and warning about) anything unrecognized.
+fn allowed_families(args: &Args) -> Vec<AddressFamily> {
+    args.address_families
+        .iter()
+        .filter_map(|family| match family.parse() {
+            Ok(family) => Some(family),
+            Err(e) => {
+                tracing::warn!("ignoring unknown --address-families entry: {}", e);
+                None
+            }
+        })
+        .collect()
+}
+
 #[tokio::main]
 async fn main() -> Result<()> {
     // Initialize logging and tracing for debugging
@@ -77,70 +123,259 @@ async fn main() -> Result<()> {
     // Parse command-line arguments
     let args = Args::parse(); //is a constant time operation
 
+    let network = args.network
+        .parse::<Network>()
+        .map_err(|_| Error::InvalidNetwork(args.network.clone()))?;
+
     // Parse remote and local addresses from command-line arguments
     // Has constant time operations
-    let remote_address = args.remote_address
-        .parse::<SocketAddr>()
-        .map_err(|_| Error::InvalidAddress("remote_address".to_string()))?;
-    let local_address = args.local_address
-        .parse::<SocketAddr>()
-        .map_err(|_| Error::InvalidAddress("local_address".to_string()))?;
-
-    // Collect initial set of peer addresses from the remote node
-    // O(m), `m` number of initial addresses collected.
-    let initial_addresses = collect_initial_addresses(&remote_address, &local_address, &args).await?;
-
-    let mut all_addresses = HashSet::new();
-    let mut addresses_to_crawl = VecDeque::new();
-
-    // O(m)
-    all_addresses.extend(initial_addresses.iter().cloned());
-    addresses_to_crawl.extend(initial_addresses);
-
-    // Continue crawling until the address limit is reached
-    // O(n)
-    while all_addresses.len() < args.address_limit && !addresses_to_crawl.is_empty() {
-        let tasks: Vec<_> = addresses_to_crawl.drain(..).map(|address| {
-            let local_address = local_address.clone();
-            tokio::spawn(async move {
-                match crawl_address(address, local_address).await {
-                    Ok(new_addresses) => new_addresses,
+    let remote_address = resolve_remote_address(&args.remote_address, network)?;
+    let local_address = resolve_local_address(&args.local_address, network)?;
+    let proxy_address = args.proxy.as_ref()
+        .map(|p| p.parse::<SocketAddr>().map_err(|_| Error::InvalidAddress("proxy".to_string())))
+        .transpose()?;
+
+    // Load (or create) the persistent peer datastore so crawls resume
+    // instead of starting from a blank slate every run. It's shared behind
+    // a mutex because the bounded worker pool below writes to it from
+    // multiple concurrent crawl tasks.
+    let mut initial_store = DataStore::load_or_create(&args.datastore_path);
+    initial_store.observe(remote_address);
+    let store = Arc::new(Mutex::new(initial_store));
+
+    run_crawl(Arc::clone(&store), local_address, proxy_address, network, &args).await?;
+
+    // Persist one last time on shutdown.
+    store.lock().await.save().context("failed to persist datastore on shutdown")?;
+
+    // Print out the addresses we classified as good, up to the limit.
+    {
+        let store = store.lock().await;
+        for (addr, _) in store.good_addresses().take(args.address_limit) {
+            println!("Peer address: {:?}", addr);
+        }
+    }
+
+    // Once the crawl has a classified set of peers, optionally keep the
+    // process alive as a DNS seed so downstream nodes can bootstrap from it.
+    if let Some(bind_addr) = &args.serve_dns {
+        let bind_addr = bind_addr
+            .parse::<SocketAddr>()
+            .map_err(|_| Error::InvalidAddress("serve_dns".to_string()))?;
+        dnsseed::serve(bind_addr, args.dns_zone.clone(), store).await?;
+    }
+
+    Ok(())
+}
+
+/// Drive the crawl with a bounded number of connections in flight at once,
+/// rather than draining the whole due-address queue into one `join_all`
+/// generation. A `Semaphore` caps concurrency; newly discovered addresses
+/// flow straight back into the datastore and are picked up by the next
+/// dispatch tick, so there's no notion of discrete "rounds" - the queue is
+/// continuously refilled until the limit is hit, we're asked to stop, or
+/// nothing is in flight and no address is due.
+async fn run_crawl(
+    store: Arc<Mutex<DataStore>>,
+    local_address: SocketAddr,
+    proxy_address: Option<SocketAddr>,
+    network: Network,
+    args: &Args,
+) -> Result<()> {
+    let semaphore = Arc::new(Semaphore::new(args.max_concurrency.max(1)));
+    let (result_tx, mut result_rx) = mpsc::channel::<(PeerAddress, Result<CrawlAttempt>)>(args.max_concurrency.max(1) * 4);
+    let mut dispatch_tick = interval(Duration::from_millis(200));
+    let mut last_save = tokio::time::Instant::now();
+    // Addresses with a task currently in flight. `next_batch` only looks at
+    // `last_tried`, which isn't updated until a crawl finishes, so without
+    // this a still-running address stays "due" and gets redispatched on
+    // every tick instead of the pool spreading out over distinct peers.
+    let mut in_flight_addresses: HashSet<PeerAddress> = HashSet::new();
+    let mut shutdown = Box::pin(tokio::signal::ctrl_c());
+
+    loop {
+        if store.lock().await.good_addresses().count() >= args.address_limit {
+            break;
+        }
+
+        tokio::select! {
+            _ = &mut shutdown => {
+                tracing::info!("Shutdown requested, stopping crawl.");
+                break;
+            }
+            _ = dispatch_tick.tick() => {
+                let available = semaphore.available_permits();
+                if available > 0 {
+                    // Ask for enough candidates to cover addresses already
+                    // in flight being filtered back out below.
+                    let candidates = store.lock().await.next_batch(available + in_flight_addresses.len());
+                    let batch: Vec<PeerAddress> = candidates
+                        .into_iter()
+                        .filter(|address| !in_flight_addresses.contains(address))
+                        .take(available)
+                        .collect();
+                    for address in batch {
+                        let permit = Arc::clone(&semaphore).acquire_owned().await
+                            .expect("semaphore is never closed");
+                        in_flight_addresses.insert(address.clone());
+                        let args_for_task = args_for_crawl(args);
+                        let result_tx = result_tx.clone();
+                        tokio::spawn(async move {
+                            let _permit = permit;
+                            let outcome = crawl_address(address.clone(), local_address, proxy_address, network, &args_for_task).await;
+                            let _ = result_tx.send((address, outcome)).await;
+                        });
+                    }
+                }
+
+                // Flush periodically so a crash doesn't lose the whole crawl.
+                if last_save.elapsed() >= Duration::from_secs(args.save_interval) {
+                    store.lock().await.save().context("failed to persist datastore")?;
+                    last_save = tokio::time::Instant::now();
+                }
+
+                if in_flight_addresses.is_empty() && store.lock().await.next_batch(1).is_empty() {
+                    break;
+                }
+            }
+            Some((address, result)) = result_rx.recv() => {
+                in_flight_addresses.remove(&address);
+                let mut store = store.lock().await;
+                match result {
+                    Ok(CrawlAttempt::Success(success)) => {
+                        for discovered in &success.addresses {
+                            store.observe(discovered.clone());
+                        }
+                        if !success.services.has(ServiceFlags::NETWORK) {
+                            // A peer that can't serve full blocks is useless
+                            // to seed other nodes from, regardless of how
+                            // current its chain tip looks.
+                            store.record_outcome(address, CrawlOutcome::ProtocolDisabled);
+                        } else if success.chain_tip_estimate >= args.min_block_height {
+                            store.record_outcome(address, CrawlOutcome::HighBlockCount {
+                                services: success.services,
+                                protocol_version: success.protocol_version,
+                                start_height: success.start_height,
+                                capabilities: success.capabilities,
+                            });
+                        } else {
+                            store.record_outcome(address, CrawlOutcome::LowBlockCount {
+                                services: success.services,
+                                protocol_version: success.protocol_version,
+                                start_height: success.start_height,
+                                capabilities: success.capabilities,
+                            });
+                        }
+                    }
+                    Ok(CrawlAttempt::TimeoutAwaitingVersion) => {
+                        tracing::warn!("Peer {:?} never sent version", address);
+                        store.record_outcome(address, CrawlOutcome::TimeoutAwaitingVersion);
+                    }
+                    Ok(CrawlAttempt::TimeoutAwaitingVerack) => {
+                        tracing::warn!("Peer {:?} never sent verack", address);
+                        store.record_outcome(address, CrawlOutcome::TimeoutAwaitingVerack);
+                    }
+                    Ok(CrawlAttempt::TimeoutDuringRequest) => {
+                        tracing::warn!("Peer {:?} never answered getheaders", address);
+                        store.record_outcome(address, CrawlOutcome::TimeoutDuringRequest);
+                    }
                     Err(e) => {
                         tracing::error!("Failed to crawl address {:?}: {:?}", address, e);
-                        HashSet::new()
+                        store.record_outcome(address, CrawlOutcome::Timeout);
                     }
                 }
-            })
-        }).collect();
-
-        // Collect results from the crawling tasks
-        let mut new_addresses = HashSet::new();
-        for task in join_all(tasks).await {
-            if let Ok(addresses) = task {
-                new_addresses.extend(addresses);
             }
         }
+    }
 
-        for addr in new_addresses.difference(&all_addresses) {
-            addresses_to_crawl.push_back(*addr);
-        }
-        all_addresses.extend(new_addresses);
+    Ok(())
+}
+
+/// Build a throwaway `Args` for a single crawl attempt, carrying over the
+/// settings that matter for a handshake but not the top-level remote/local
+/// addresses (those are per-crawl).
+fn args_for_crawl(args: &Args) -> Args {
+    Args {
+        remote_address: String::new(),
+        local_address: String::new(),
+        network: args.network.clone(),
+        address_limit: args.address_limit,
+        connection_timeout: args.connection_timeout,
+        user_agent: args.user_agent.clone(),
+        datastore_path: args.datastore_path.clone(),
+        save_interval: args.save_interval,
+        serve_dns: None,
+        dns_zone: args.dns_zone.clone(),
+        address_families: args.address_families.clone(),
+        proxy: args.proxy.clone(),
+        max_concurrency: args.max_concurrency,
+        min_block_height: args.min_block_height,
+        locator_block_hash: args.locator_block_hash.clone(),
+        locator_block_height: args.locator_block_height,
     }
+}
 
-    // Print out collected peer addresses, up to the limit
-    // O(n)
-    for addr in all_addresses.iter().take(args.address_limit) {
-        println!("Peer address: {:?}", addr);
+/// The standard listening port for each network, used when the user omits
+/// a port from `--remote-address`/`--local-address`.
+fn default_port(network: Network) -> u16 {
+    match network {
+        Network::Bitcoin => 8333,
+        Network::Testnet => 18333,
+        Network::Signet => 38333,
+        Network::Regtest => 18444,
+        _ => 8333,
     }
+}
 
-    // O(1)+O(m)+O(m)+O(n)+O(n)=O(m+n)
+/// Parse `--remote-address`, filling in the network's default port if the
+/// user didn't specify one.
+fn resolve_remote_address(raw: &str, network: Network) -> std::result::Result<PeerAddress, Error> {
+    if let Ok(address) = raw.parse::<PeerAddress>() {
+        return Ok(address);
+    }
+    raw.parse::<std::net::IpAddr>()
+        .map(|ip| PeerAddress::Socket(SocketAddr::new(ip, default_port(network))))
+        .map_err(|_| Error::InvalidAddress("remote_address".to_string()))
+}
 
-    Ok(())
+/// Parse `--local-address`, filling in the network's default port if the
+/// user didn't specify one.
+fn resolve_local_address(raw: &str, network: Network) -> std::result::Result<SocketAddr, Error> {
+    if let Ok(address) = raw.parse::<SocketAddr>() {
+        return Ok(address);
+    }
+    raw.parse::<std::net::IpAddr>()
+        .map(|ip| SocketAddr::new(ip, default_port(network)))
+        .map_err(|_| Error::InvalidAddress("local_address".to_string()))
 }
 
-/// Establish a TCP connection to the specified remote address with a timeout.
-pub async fn connect(remote_address: &SocketAddr, timeout_duration: u64) -> Result<Framed<TcpStream, BitcoinCodec>> {
-    let connection = TcpStream::connect(remote_address).map_err(|e| anyhow::anyhow!("Connection failed: {:?}", e));
+/// A placeholder routable address used to fill the `version` message's
+/// receiver field when dialing a peer (like a `.onion` address) that has
+/// no IP of its own.
+const NO_ROUTABLE_ADDRESS: SocketAddr = SocketAddr::V4(SocketAddrV4::new(Ipv4Addr::UNSPECIFIED, 0));
+
+/// Establish a connection to the specified peer address with a timeout,
+/// dialing directly for IPv4/IPv6 addresses and through a SOCKS5 proxy for
+/// `.onion` addresses.
+pub async fn connect(
+    remote_address: &PeerAddress,
+    timeout_duration: u64,
+    proxy_address: Option<SocketAddr>,
+) -> Result<Framed<TcpStream, BitcoinCodec>> {
+    let connection = async {
+        match remote_address {
+            PeerAddress::Socket(addr) => {
+                TcpStream::connect(addr).await.map_err(|e| anyhow::anyhow!("Connection failed: {:?}", e))
+            }
+            PeerAddress::Onion { host, port } => {
+                let proxy = proxy_address
+                    .ok_or_else(|| anyhow::anyhow!("connecting to {} requires --proxy", remote_address))?;
+                socks::connect_via_socks5(proxy, host, *port)
+                    .await
+                    .map_err(|e| anyhow::anyhow!("Proxied connection failed: {:?}", e))
+            }
+        }
+    };
     let stream = timeout(Duration::from_secs(timeout_duration), connection)
         .map_err(|e| anyhow::anyhow!("Connection timed out: {:?}", e))
         .await??;
@@ -148,73 +383,124 @@ pub async fn connect(remote_address: &SocketAddr, timeout_duration: u64) -> Resu
     Ok(framed)
 }
 
-/// Collect initial peer addresses from a connected node.
+/// Collect initial peer addresses from a connected node, also probing its
+/// chain tip via `getheaders` so the caller can classify it as
+/// `HighBlockCount` or `LowBlockCount` from an estimate derived from the
+/// `headers` the peer actually returns (locator height plus header count)
+/// rather than trusting the `version` message's self-reported
+/// `start_height` alone.
 async fn collect_initial_addresses(
-    remote_address: &SocketAddr,
+    remote_address: &PeerAddress,
     local_address: &SocketAddr,
+    proxy_address: Option<SocketAddr>,
+    network: Network,
     args: &Args,
-) -> Result<HashSet<SocketAddr>> {
-    let mut stream = connect(remote_address, args.connection_timeout).await?;
-    let version_message = RawNetworkMessage::new(
-        Network::Bitcoin.magic(),
-        NetworkMessage::Version(build_version_message(remote_address, local_address, &args.user_agent)),
-    );
-
-    // Send version message to initiate handshake
+) -> Result<CrawlAttempt> {
+    let locator_hash: BlockHash = args.locator_block_hash.parse()
+        .with_context(|| format!("invalid --locator-block-hash {:?}", args.locator_block_hash))?;
+    let magic = network.magic();
+    let mut stream = connect(remote_address, args.connection_timeout, proxy_address).await?;
+    let receiver_address = remote_address.as_socket_addr().unwrap_or(NO_ROUTABLE_ADDRESS);
+
+    let handshake = match perform_handshake(
+        &mut stream,
+        magic,
+        &receiver_address,
+        local_address,
+        &args.user_agent,
+        args.connection_timeout,
+    ).await? {
+        HandshakeOutcome::Established(handshake) => handshake,
+        HandshakeOutcome::TimeoutAwaitingVersion => return Ok(CrawlAttempt::TimeoutAwaitingVersion),
+        HandshakeOutcome::TimeoutAwaitingVerack => return Ok(CrawlAttempt::TimeoutAwaitingVerack),
+    };
+    tracing::info!("Handshake established with {}: {:?}", remote_address, handshake);
+
+    // Now that version/verack is settled, request addresses and probe the
+    // peer's chain tip.
+    stream
+        .send(RawNetworkMessage::new(magic, NetworkMessage::GetAddr))
+        .await
+        .map_err(|e| anyhow::anyhow!("Failed to send getaddr message: {:?}", e))?;
+    let getheaders = GetHeadersMessage::new(vec![locator_hash], BlockHash::all_zeros());
     stream
-        .send(version_message)
+        .send(RawNetworkMessage::new(magic, NetworkMessage::GetHeaders(getheaders)))
         .await
-        .map_err(|e| anyhow::anyhow!("Failed to send version message: {:?}", e))?;
+        .map_err(|e| anyhow::anyhow!("Failed to send getheaders message: {:?}", e))?;
 
     let mut peer_addresses = HashSet::new();
-    let mut verack_sent = false;
-    let mut getaddr_sent = false;
+    // `None` until the peer's `headers` reply arrives; `Some(n)` once it
+    // has, carrying the total number of headers returned past our locator.
+    let mut headers_count: Option<u32> = None;
+    let families = allowed_families(args);
+
+    // Bound the whole probe by the connection timeout: a peer that
+    // completes the handshake but never answers `getheaders` would
+    // otherwise hang this task forever.
+    let deadline = tokio::time::Instant::now() + Duration::from_secs(args.connection_timeout);
 
-    while let Some(result) = stream.next().await {
-        match result {
+    loop {
+        if headers_count.is_some() && peer_addresses.len() >= args.address_limit {
+            break;
+        }
+
+        let message = tokio::select! {
+            _ = sleep_until(deadline) => {
+                if headers_count.is_some() {
+                    break;
+                }
+                return Ok(CrawlAttempt::TimeoutDuringRequest);
+            }
+            next = stream.next() => match next {
+                Some(message) => message,
+                None => {
+                    // The peer hung up before ever answering `getheaders` -
+                    // treat that the same as a timeout rather than falling
+                    // through to a tip estimate of zero headers received.
+                    if headers_count.is_none() {
+                        return Ok(CrawlAttempt::TimeoutDuringRequest);
+                    }
+                    break;
+                }
+            },
+        };
+
+        match message {
             Ok(message) => {
+                if message.magic() != magic {
+                    tracing::warn!(
+                        "Rejecting message with wrong network magic from {}: got {:?}, expected {:?}",
+                        remote_address, message.magic(), magic
+                    );
+                    continue;
+                }
                 let payload = message.payload().clone(); // Clone payload to avoid lifetime issues
                 match payload {
-                    NetworkMessage::Version(remote_version) => {
-                        tracing::info!("Received Version Message: {:?}", remote_version);
-                        if !verack_sent {
-                            // Send verack message to complete handshake
-                            stream
-                                .send(RawNetworkMessage::new(
-                                    Network::Bitcoin.magic(),
-                                    NetworkMessage::Verack,
-                                ))
-                                .await
-                                .map_err(|e| anyhow::anyhow!("Failed to send verack message: {:?}", e))?;
-                            verack_sent = true;
-                        }
-                        if !getaddr_sent {
-                            // Request a list of addresses from the remote node
-                            stream
-                                .send(RawNetworkMessage::new(
-                                    Network::Bitcoin.magic(),
-                                    NetworkMessage::GetAddr,
-                                ))
-                                .await
-                                .map_err(|e| anyhow::anyhow!("Failed to send getaddr message: {:?}", e))?;
-                            getaddr_sent = true;
-                        }
-                    }
                     NetworkMessage::Addr(addresses) => {
                         tracing::info!("Received Addr Message with {} addresses", addresses.len());
-                        // Filter and collect IPv4 addresses
+                        // The legacy `addr` message can only carry IPv4/IPv6.
                         for (_, address) in addresses {
-                            if is_ipv4(&address) {
-                                if let Ok(socket_addr) = address.socket_addr() {
-                                    peer_addresses.insert(socket_addr);
+                            if let (Some(family), Ok(socket_addr)) = (address_family(&address), address.socket_addr()) {
+                                if families.contains(&family) {
+                                    peer_addresses.insert(PeerAddress::Socket(socket_addr));
                                 }
                             }
                         }
-                        // Break the loop if we have enough addresses
-                        if peer_addresses.len() >= args.address_limit {
-                            break;
+                    }
+                    NetworkMessage::AddrV2(addresses) => {
+                        tracing::info!("Received AddrV2 Message with {} addresses", addresses.len());
+                        for AddrV2Message { addr, port, .. } in addresses {
+                            if let Some(peer_address) = PeerAddress::from_addr_v2(&addr, port) {
+                                if families.contains(&peer_address.family()) {
+                                    peer_addresses.insert(peer_address);
+                                }
+                            }
                         }
                     }
+                    NetworkMessage::Headers(headers) => {
+                        tracing::info!("Received Headers Message with {} headers", headers.len());
+                        *headers_count.get_or_insert(0) += headers.len() as u32;
+                    }
                     _ => {}
                 }
             }
@@ -224,20 +510,34 @@ async fn collect_initial_addresses(
         }
     }
 
-    Ok(peer_addresses)
+    // Estimate the peer's chain tip as our locator's height plus however
+    // many headers it returned past that point, rather than trusting its
+    // self-reported `version.start_height`. By this point `headers_count`
+    // is always `Some` - both ways of never getting a `headers` reply
+    // (timeout, stream closed) return `TimeoutDuringRequest` above instead
+    // of falling through to here.
+    let chain_tip_estimate = args.locator_block_height.saturating_add(headers_count.unwrap_or(0) as i32);
+
+    Ok(CrawlAttempt::Success(CrawlSuccess {
+        addresses: peer_addresses,
+        services: handshake.services,
+        protocol_version: handshake.protocol_version,
+        start_height: handshake.start_height,
+        capabilities: handshake.capabilities,
+        chain_tip_estimate,
+    }))
 }
 
-/// Crawl a specific address to collect more peer addresses.
-async fn crawl_address(address: SocketAddr, local_address: SocketAddr) -> Result<HashSet<SocketAddr>> {
-    let mut stream = connect(&address, 10).await?; // Use default timeout here
-    let new_addresses = collect_initial_addresses(&address, &local_address, &Args {
-        remote_address: String::new(),
-        local_address: String::new(),
-        address_limit: 5000,
-        connection_timeout: 10,
-        user_agent: String::new(),
-    }).await?;
-    Ok(new_addresses)
+/// Crawl a specific address to collect more peer addresses, folding the
+/// result into an outcome the datastore can schedule retries from.
+async fn crawl_address(
+    address: PeerAddress,
+    local_address: SocketAddr,
+    proxy_address: Option<SocketAddr>,
+    network: Network,
+    args: &Args,
+) -> Result<CrawlAttempt> {
+    collect_initial_addresses(&address, &local_address, proxy_address, network, args).await
 }
 
  #[cfg(test)]
@@ -265,14 +565,21 @@ mod tests {
             let (socket, _) = listener.accept().await.unwrap();
         });
 
-        let result = connect(&local_addr, 5).await;
+        let result = connect(&PeerAddress::Socket(local_addr), 5, None).await;
         assert!(result.is_ok());
     }
 
     #[tokio::test]
     async fn test_connect_timeout() {
         let remote_addr = "127.0.0.1:65535".parse::<SocketAddr>().unwrap(); // Unused port
-        let result = connect(&remote_addr, 1).await;
+        let result = connect(&PeerAddress::Socket(remote_addr), 1, None).await;
+        assert!(result.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_connect_onion_without_proxy_fails() {
+        let onion = PeerAddress::Onion { host: "exampleexampleexampleexampleexampleexampleexamplea.onion".to_string(), port: 8333 };
+        let result = connect(&onion, 1, None).await;
         assert!(result.is_err());
     }
    
@@ -1,8 +1,16 @@
 // message.rs
-use bitcoin::p2p::{Address, ServiceFlags};
+use bitcoin::p2p::{Address, Magic, ServiceFlags};
+use bitcoin::p2p::message::{NetworkMessage, RawNetworkMessage};
 use bitcoin::p2p::message_network::VersionMessage;
 use std::net::SocketAddr;
+use std::time::Duration;
+use futures::{SinkExt, StreamExt};
 use rand::Rng;
+use tokio::net::TcpStream;
+use tokio::time::sleep_until;
+use tokio_util::codec::Framed;
+
+use crate::codec::BitcoinCodec;
 
 /// Build a version message for the Bitcoin protocol.
 pub fn build_version_message(
@@ -30,10 +38,237 @@ pub fn build_version_message(
     )
 }
 
+/// Feature flags a peer announces between `version` and `verack`. None of
+/// these are required for a handshake to complete, but they change how the
+/// rest of the session should behave - most importantly `sendaddrv2`, which
+/// means `getaddr` should be answered (and read) as `addrv2` rather than the
+/// legacy `addr` message.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct PeerCapabilities {
+    pub sendheaders: bool,
+    pub wtxidrelay: bool,
+    pub sendcmpct: bool,
+    pub feefilter: bool,
+    pub sendaddrv2: bool,
+}
+
+impl PeerCapabilities {
+    const SENDHEADERS: u8 = 0b0000_0001;
+    const WTXIDRELAY: u8 = 0b0000_0010;
+    const SENDCMPCT: u8 = 0b0000_0100;
+    const FEEFILTER: u8 = 0b0000_1000;
+    const SENDADDRV2: u8 = 0b0001_0000;
+
+    /// Pack the flags into a single byte for compact persistence.
+    pub fn to_bits(self) -> u8 {
+        let mut bits = 0u8;
+        if self.sendheaders { bits |= Self::SENDHEADERS; }
+        if self.wtxidrelay { bits |= Self::WTXIDRELAY; }
+        if self.sendcmpct { bits |= Self::SENDCMPCT; }
+        if self.feefilter { bits |= Self::FEEFILTER; }
+        if self.sendaddrv2 { bits |= Self::SENDADDRV2; }
+        bits
+    }
+
+    /// Inverse of [`PeerCapabilities::to_bits`].
+    pub fn from_bits(bits: u8) -> Self {
+        PeerCapabilities {
+            sendheaders: bits & Self::SENDHEADERS != 0,
+            wtxidrelay: bits & Self::WTXIDRELAY != 0,
+            sendcmpct: bits & Self::SENDCMPCT != 0,
+            feefilter: bits & Self::FEEFILTER != 0,
+            sendaddrv2: bits & Self::SENDADDRV2 != 0,
+        }
+    }
+}
+
+/// What a completed handshake negotiated with a peer.
+#[derive(Debug, Clone)]
+pub struct HandshakeResult {
+    pub protocol_version: i32,
+    pub services: ServiceFlags,
+    pub start_height: i32,
+    pub capabilities: PeerCapabilities,
+}
+
+/// The result of attempting a handshake within the allotted time - either
+/// it completed, or it didn't, and we know which half was still missing
+/// when the deadline hit.
+pub enum HandshakeOutcome {
+    Established(HandshakeResult),
+    TimeoutAwaitingVersion,
+    TimeoutAwaitingVerack,
+}
+
+/// Drive a full version/verack handshake against a freshly connected peer.
+///
+/// Sends `version` (and our own `sendaddrv2`, announcing that we understand
+/// BIP155 addresses), then waits until *both* the peer's `version` and its
+/// `verack` have arrived before considering the connection established -
+/// replying with our own `verack` as soon as the peer's `version` is seen,
+/// same as Bitcoin Core. Any feature messages the peer sends in the
+/// meantime are folded into the returned `PeerCapabilities` rather than
+/// acted on here; callers decide what to do with them.
+///
+/// The whole wait is bounded by `timeout_duration`: a peer that accepts the
+/// TCP connection and then goes silent would otherwise hang the caller
+/// forever. Which `TimeoutAwaiting*` variant comes back depends on how far
+/// the handshake got before the deadline hit.
+pub async fn perform_handshake(
+    stream: &mut Framed<TcpStream, BitcoinCodec>,
+    magic: Magic,
+    receiver_address: &SocketAddr,
+    sender_address: &SocketAddr,
+    user_agent: &str,
+    timeout_duration: u64,
+) -> anyhow::Result<HandshakeOutcome> {
+    stream
+        .send(RawNetworkMessage::new(
+            magic,
+            NetworkMessage::Version(build_version_message(receiver_address, sender_address, user_agent)),
+        ))
+        .await
+        .map_err(|e| anyhow::anyhow!("Failed to send version message: {:?}", e))?;
+    stream
+        .send(RawNetworkMessage::new(magic, NetworkMessage::SendAddrV2))
+        .await
+        .map_err(|e| anyhow::anyhow!("Failed to send sendaddrv2 message: {:?}", e))?;
+
+    let mut verack_sent = false;
+    let mut remote_version: Option<VersionMessage> = None;
+    let mut remote_verack_received = false;
+    let mut capabilities = PeerCapabilities::default();
+    let deadline = tokio::time::Instant::now() + Duration::from_secs(timeout_duration);
+
+    while remote_version.is_none() || !remote_verack_received {
+        let message = tokio::select! {
+            _ = sleep_until(deadline) => {
+                return Ok(if remote_version.is_none() {
+                    HandshakeOutcome::TimeoutAwaitingVersion
+                } else {
+                    HandshakeOutcome::TimeoutAwaitingVerack
+                });
+            }
+            next = stream.next() => next
+                .ok_or_else(|| anyhow::anyhow!("connection closed during handshake"))?
+                .map_err(|e| anyhow::anyhow!("decoding error during handshake: {:?}", e))?,
+        };
+
+        if message.magic() != magic {
+            tracing::warn!(
+                "Rejecting handshake message with wrong network magic: got {:?}, expected {:?}",
+                message.magic(), magic
+            );
+            continue;
+        }
+
+        match message.payload().clone() {
+            NetworkMessage::Version(version) => {
+                tracing::info!("Received Version Message: {:?}", version);
+                remote_version = Some(version);
+                if !verack_sent {
+                    stream
+                        .send(RawNetworkMessage::new(magic, NetworkMessage::Verack))
+                        .await
+                        .map_err(|e| anyhow::anyhow!("Failed to send verack message: {:?}", e))?;
+                    verack_sent = true;
+                }
+            }
+            NetworkMessage::Verack => {
+                remote_verack_received = true;
+            }
+            NetworkMessage::SendHeaders => capabilities.sendheaders = true,
+            NetworkMessage::WtxidRelay => capabilities.wtxidrelay = true,
+            NetworkMessage::SendCmpct(_) => capabilities.sendcmpct = true,
+            NetworkMessage::FeeFilter(_) => capabilities.feefilter = true,
+            NetworkMessage::SendAddrV2 => capabilities.sendaddrv2 = true,
+            _ => {}
+        }
+    }
+
+    let remote_version = remote_version.expect("loop only exits once both fields are set");
+    Ok(HandshakeOutcome::Established(HandshakeResult {
+        protocol_version: remote_version.version,
+        services: remote_version.services,
+        start_height: remote_version.start_height,
+        capabilities,
+    }))
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
     use std::net::{IpAddr, Ipv4Addr};
+    use tokio::net::TcpListener;
+
+    #[tokio::test]
+    async fn handshake_waits_for_both_remote_version_and_verack() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+
+        let peer = tokio::spawn(async move {
+            let (socket, _) = listener.accept().await.unwrap();
+            let mut peer_stream = Framed::new(socket, BitcoinCodec {});
+
+            // Our `version` arrives first...
+            let first = peer_stream.next().await.unwrap().unwrap();
+            assert!(matches!(first.payload(), NetworkMessage::Version(_)));
+            // ...then our `sendaddrv2` announcement.
+            let second = peer_stream.next().await.unwrap().unwrap();
+            assert!(matches!(second.payload(), NetworkMessage::SendAddrV2));
+
+            let magic = first.magic();
+            let local = addr;
+            peer_stream
+                .send(RawNetworkMessage::new(
+                    magic,
+                    NetworkMessage::Version(build_version_message(&local, &local, "/test:0.0.0/")),
+                ))
+                .await
+                .unwrap();
+            peer_stream.send(RawNetworkMessage::new(magic, NetworkMessage::SendHeaders)).await.unwrap();
+
+            // Wait for our verack before sending the peer's own.
+            let reply = peer_stream.next().await.unwrap().unwrap();
+            assert!(matches!(reply.payload(), NetworkMessage::Verack));
+            peer_stream.send(RawNetworkMessage::new(magic, NetworkMessage::Verack)).await.unwrap();
+        });
+
+        let mut stream = Framed::new(tokio::net::TcpStream::connect(addr).await.unwrap(), BitcoinCodec {});
+        let outcome = perform_handshake(&mut stream, Magic::BITCOIN, &addr, &addr, "/test:0.0.0/", 5).await.unwrap();
+
+        let HandshakeOutcome::Established(result) = outcome else {
+            panic!("expected a completed handshake");
+        };
+        assert!(result.capabilities.sendheaders);
+        assert!(!result.capabilities.sendaddrv2);
+        peer.await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn handshake_times_out_awaiting_remote_version() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+
+        let peer = tokio::spawn(async move {
+            // Accept the connection but never speak - the handshake should
+            // time out rather than hang forever.
+            let _socket = listener.accept().await.unwrap();
+            tokio::time::sleep(Duration::from_secs(2)).await;
+        });
+
+        let mut stream = Framed::new(tokio::net::TcpStream::connect(addr).await.unwrap(), BitcoinCodec {});
+        let outcome = perform_handshake(&mut stream, Magic::BITCOIN, &addr, &addr, "/test:0.0.0/", 1).await.unwrap();
+
+        assert!(matches!(outcome, HandshakeOutcome::TimeoutAwaitingVersion));
+        peer.abort();
+    }
+
+    #[test]
+    fn capabilities_round_trip_through_bits() {
+        let capabilities = PeerCapabilities { sendheaders: true, wtxidrelay: false, sendcmpct: true, feefilter: false, sendaddrv2: true };
+        assert_eq!(PeerCapabilities::from_bits(capabilities.to_bits()), capabilities);
+    }
 
     #[test]
     fn test_build_version_message() {
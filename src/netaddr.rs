@@ -0,0 +1,183 @@
+// netaddr.rs
+//
+// `SocketAddr` only covers IPv4/IPv6, but BIP155 (`addrv2`) peers can also
+// advertise Tor v3 onion services, which have no IP at all. `PeerAddress`
+// is the common type the rest of the crawler (datastore, crawl loop,
+// connect) threads around instead of `SocketAddr`, so onion peers are
+// first-class citizens rather than something bolted on afterwards.
+
+use std::fmt;
+use std::net::{Ipv4Addr, Ipv6Addr, SocketAddr};
+use std::str::FromStr;
+
+use bitcoin::p2p::address::AddrV2;
+use sha3::{Digest, Sha3_256};
+
+/// The network family a `PeerAddress` belongs to, used for the
+/// `--address-families` filter.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum AddressFamily {
+    Ipv4,
+    Ipv6,
+    Onion,
+}
+
+impl FromStr for AddressFamily {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "ipv4" => Ok(AddressFamily::Ipv4),
+            "ipv6" => Ok(AddressFamily::Ipv6),
+            "onion" | "tor" => Ok(AddressFamily::Onion),
+            other => Err(format!("unknown address family: {other}")),
+        }
+    }
+}
+
+/// An address a peer can be reached at: a regular socket address, or a Tor
+/// v3 onion service.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum PeerAddress {
+    Socket(SocketAddr),
+    Onion { host: String, port: u16 },
+}
+
+impl PeerAddress {
+    pub fn family(&self) -> AddressFamily {
+        match self {
+            PeerAddress::Socket(SocketAddr::V4(_)) => AddressFamily::Ipv4,
+            PeerAddress::Socket(SocketAddr::V6(_)) => AddressFamily::Ipv6,
+            PeerAddress::Onion { .. } => AddressFamily::Onion,
+        }
+    }
+
+    pub fn as_socket_addr(&self) -> Option<SocketAddr> {
+        match self {
+            PeerAddress::Socket(addr) => Some(*addr),
+            PeerAddress::Onion { .. } => None,
+        }
+    }
+
+    pub fn port(&self) -> u16 {
+        match self {
+            PeerAddress::Socket(addr) => addr.port(),
+            PeerAddress::Onion { port, .. } => *port,
+        }
+    }
+
+    /// Build a `PeerAddress` from a decoded BIP155 `AddrV2` entry, if we
+    /// support its network. `TorV2`, `I2p` and unknown networks are not
+    /// something we can dial, so they're dropped by the caller.
+    pub fn from_addr_v2(addr: &AddrV2, port: u16) -> Option<PeerAddress> {
+        match addr {
+            AddrV2::Ipv4(ip) => Some(PeerAddress::Socket(SocketAddr::new((*ip).into(), port))),
+            AddrV2::Ipv6(ip) => Some(PeerAddress::Socket(SocketAddr::new((*ip).into(), port))),
+            AddrV2::TorV3(pubkey) => Some(PeerAddress::Onion { host: encode_onion_v3(pubkey), port }),
+            AddrV2::TorV2(_) | AddrV2::I2p(_) | AddrV2::Cjdns(_) | AddrV2::Unknown(_, _) => None,
+        }
+    }
+}
+
+impl fmt::Display for PeerAddress {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            PeerAddress::Socket(addr) => write!(f, "{addr}"),
+            PeerAddress::Onion { host, port } => write!(f, "{host}:{port}"),
+        }
+    }
+}
+
+impl FromStr for PeerAddress {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        if let Ok(addr) = s.parse::<SocketAddr>() {
+            return Ok(PeerAddress::Socket(addr));
+        }
+        let (host, port) = s.rsplit_once(':').ok_or_else(|| format!("missing port in address: {s}"))?;
+        if !host.ends_with(".onion") {
+            return Err(format!("unsupported address: {s}"));
+        }
+        let port: u16 = port.parse().map_err(|_| format!("invalid port in address: {s}"))?;
+        Ok(PeerAddress::Onion { host: host.to_string(), port })
+    }
+}
+
+/// Encode a Tor v3 onion service public key as the `<56 chars>.onion` host
+/// name, per the Tor rend-spec-v3 address format:
+/// `base32(pubkey || checksum || version)`, where
+/// `checksum = sha3_256(".onion checksum" || pubkey || version)[..2]`.
+fn encode_onion_v3(pubkey: &[u8; 32]) -> String {
+    const VERSION: u8 = 3;
+    let mut hasher = Sha3_256::new();
+    hasher.update(b".onion checksum");
+    hasher.update(pubkey);
+    hasher.update([VERSION]);
+    let digest = hasher.finalize();
+
+    let mut payload = Vec::with_capacity(32 + 2 + 1);
+    payload.extend_from_slice(pubkey);
+    payload.extend_from_slice(&digest[..2]);
+    payload.push(VERSION);
+
+    format!("{}.onion", base32_encode(&payload).to_lowercase())
+}
+
+/// RFC 4648 base32 encoding (no padding), hand-rolled to avoid pulling in a
+/// dedicated crate for a single call site.
+fn base32_encode(data: &[u8]) -> String {
+    const ALPHABET: &[u8; 32] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZ234567";
+    let mut out = String::with_capacity((data.len() * 8 + 4) / 5);
+    let mut buffer: u32 = 0;
+    let mut bits = 0u32;
+    for &byte in data {
+        buffer = (buffer << 8) | byte as u32;
+        bits += 8;
+        while bits >= 5 {
+            bits -= 5;
+            out.push(ALPHABET[((buffer >> bits) & 0x1F) as usize] as char);
+        }
+    }
+    if bits > 0 {
+        out.push(ALPHABET[((buffer << (5 - bits)) & 0x1F) as usize] as char);
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::net::{IpAddr, Ipv4Addr as V4};
+
+    #[test]
+    fn socket_addresses_round_trip_through_display_and_from_str() {
+        let addr = PeerAddress::Socket(SocketAddr::new(IpAddr::V4(V4::new(1, 2, 3, 4)), 8333));
+        let rendered = addr.to_string();
+        assert_eq!(rendered.parse::<PeerAddress>().unwrap(), addr);
+    }
+
+    #[test]
+    fn onion_addresses_round_trip_through_display_and_from_str() {
+        let addr = PeerAddress::Onion { host: "exampleexampleexampleexampleexampleexampleexamplea.onion".to_string(), port: 8333 };
+        let rendered = addr.to_string();
+        assert_eq!(rendered.parse::<PeerAddress>().unwrap(), addr);
+    }
+
+    #[test]
+    fn families_are_classified_correctly() {
+        let v4 = PeerAddress::Socket(SocketAddr::new(IpAddr::V4(V4::new(1, 2, 3, 4)), 8333));
+        let onion = PeerAddress::Onion { host: "abc.onion".to_string(), port: 8333 };
+        assert_eq!(v4.family(), AddressFamily::Ipv4);
+        assert_eq!(onion.family(), AddressFamily::Onion);
+    }
+
+    #[test]
+    fn tor_v3_pubkeys_encode_to_56_char_onion_hosts() {
+        let pubkey = [0u8; 32];
+        let host = encode_onion_v3(&pubkey);
+        // 56 base32 chars + ".onion"
+        assert_eq!(host.len(), 56 + 6);
+        assert!(host.ends_with(".onion"));
+    }
+}
@@ -0,0 +1,75 @@
+// socks.rs
+//
+// A minimal SOCKS5 client, just enough to open a CONNECT tunnel through a
+// local Tor daemon (or any other SOCKS5 proxy) to reach `.onion` peers.
+// No authentication, no UDP ASSOCIATE, no BIND - a crawler only ever needs
+// outbound CONNECT.
+
+use std::net::SocketAddr;
+
+use anyhow::{anyhow, Result};
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::TcpStream;
+
+const SOCKS_VERSION: u8 = 0x05;
+const METHOD_NO_AUTH: u8 = 0x00;
+const CMD_CONNECT: u8 = 0x01;
+const ATYP_DOMAIN: u8 = 0x03;
+const ATYP_IPV4: u8 = 0x01;
+const ATYP_IPV6: u8 = 0x04;
+
+/// Open a TCP stream to `proxy_addr` and ask it to `CONNECT` to
+/// `target_host:target_port` on our behalf. The target is sent as a domain
+/// name rather than a resolved IP so the proxy (e.g. Tor) does its own
+/// resolution - required for `.onion` hosts, which have no IP at all.
+pub async fn connect_via_socks5(proxy_addr: SocketAddr, target_host: &str, target_port: u16) -> Result<TcpStream> {
+    let mut stream = TcpStream::connect(proxy_addr).await?;
+
+    // Greeting: offer "no authentication" only.
+    stream.write_all(&[SOCKS_VERSION, 1, METHOD_NO_AUTH]).await?;
+    let mut greeting_reply = [0u8; 2];
+    stream.read_exact(&mut greeting_reply).await?;
+    if greeting_reply[0] != SOCKS_VERSION || greeting_reply[1] != METHOD_NO_AUTH {
+        return Err(anyhow!("SOCKS5 proxy rejected no-auth handshake: {:?}", greeting_reply));
+    }
+
+    // CONNECT request, target encoded as a domain name.
+    let mut request = vec![SOCKS_VERSION, CMD_CONNECT, 0x00, ATYP_DOMAIN];
+    if target_host.len() > u8::MAX as usize {
+        return Err(anyhow!("target host name too long for SOCKS5: {}", target_host));
+    }
+    request.push(target_host.len() as u8);
+    request.extend_from_slice(target_host.as_bytes());
+    request.extend_from_slice(&target_port.to_be_bytes());
+    stream.write_all(&request).await?;
+
+    let mut reply_header = [0u8; 4];
+    stream.read_exact(&mut reply_header).await?;
+    if reply_header[0] != SOCKS_VERSION {
+        return Err(anyhow!("unexpected SOCKS version in reply: {}", reply_header[0]));
+    }
+    if reply_header[1] != 0x00 {
+        return Err(anyhow!("SOCKS5 CONNECT failed with reply code {}", reply_header[1]));
+    }
+
+    // Drain the bound address the proxy echoes back; we don't need it.
+    match reply_header[3] {
+        ATYP_IPV4 => {
+            let mut discard = [0u8; 4 + 2];
+            stream.read_exact(&mut discard).await?;
+        }
+        ATYP_IPV6 => {
+            let mut discard = [0u8; 16 + 2];
+            stream.read_exact(&mut discard).await?;
+        }
+        ATYP_DOMAIN => {
+            let mut len = [0u8; 1];
+            stream.read_exact(&mut len).await?;
+            let mut discard = vec![0u8; len[0] as usize + 2];
+            stream.read_exact(&mut discard).await?;
+        }
+        other => return Err(anyhow!("unsupported SOCKS5 bound-address type {}", other)),
+    }
+
+    Ok(stream)
+}
@@ -2,8 +2,10 @@
 use tracing::level_filters::LevelFilter;
 use tracing_subscriber::prelude::*;
 use tracing_subscriber::EnvFilter;
-use std::net::{SocketAddr, IpAddr};
-use bitcoin::p2p::{Address, ServiceFlags};
+use std::net::SocketAddr;
+use bitcoin::p2p::Address;
+
+use crate::netaddr::AddressFamily;
 
 /// Initialize logging and tracing for debugging.
 pub fn init_tracing() {
@@ -24,17 +26,21 @@ pub fn init_tracing() {
         .init();
 }
 
-/// Check if the address is IPv4.
-pub fn is_ipv4(address: &Address) -> bool {
-    matches!(address.socket_addr(), Ok(SocketAddr::V4(_)))
+/// Classify a legacy `addr` entry's family. Legacy addresses can only ever
+/// be IPv4 or IPv6 - `addrv2` is what's needed to learn about onion peers.
+pub fn address_family(address: &Address) -> Option<AddressFamily> {
+    match address.socket_addr() {
+        Ok(SocketAddr::V4(_)) => Some(AddressFamily::Ipv4),
+        Ok(SocketAddr::V6(_)) => Some(AddressFamily::Ipv6),
+        Err(_) => None,
+    }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
-    use std::net::Ipv4Addr;
-    use std::str::FromStr;
-    use bitcoin::p2p::Address;
+    use std::net::{IpAddr, Ipv4Addr, Ipv6Addr};
+    use bitcoin::p2p::ServiceFlags;
 
     #[test]
     fn test_init_tracing() {
@@ -43,11 +49,16 @@ mod tests {
     }
 
     #[test]
-    fn test_is_ipv4() {
-        let ipv4_addr = Ipv4Addr::new(192, 168, 1, 1);
-        let socket_addr_v4 = SocketAddr::new(IpAddr::V4(ipv4_addr), 8333);
-        let ipv4_address = Address::new(&socket_addr_v4, ServiceFlags::NONE);
+    fn test_address_family_ipv4() {
+        let socket_addr_v4 = SocketAddr::new(IpAddr::V4(Ipv4Addr::new(192, 168, 1, 1)), 8333);
+        let address = Address::new(&socket_addr_v4, ServiceFlags::NONE);
+        assert_eq!(address_family(&address), Some(AddressFamily::Ipv4));
+    }
 
-        assert!(is_ipv4(&ipv4_address));
+    #[test]
+    fn test_address_family_ipv6() {
+        let socket_addr_v6 = SocketAddr::new(IpAddr::V6(Ipv6Addr::LOCALHOST), 8333);
+        let address = Address::new(&socket_addr_v6, ServiceFlags::NONE);
+        assert_eq!(address_family(&address), Some(AddressFamily::Ipv6));
     }
 }